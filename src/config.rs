@@ -0,0 +1,109 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::connman::{ResolvOptions, Service};
+use crate::resolvconf::BackendKind;
+
+
+/// Default path of the config file, overridable with `-c`/`--config`.
+pub const DEFAULT_CONFIG_PATH: &str = "/etc/connman-resolvconf.toml";
+
+/// User-configurable filtering and static DNS overrides, loaded from a TOML
+/// file at startup.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Service IDs or interface names to manage; if non-empty, every other
+    /// service is ignored.
+    pub allow: HashSet<String>,
+    /// Service IDs or interface names to never manage.
+    pub deny: HashSet<String>,
+    /// Nameservers appended to every generated resolv.conf block.
+    pub append_nameservers: Vec<String>,
+    /// Search domains appended to every generated resolv.conf block.
+    pub append_domains: Vec<String>,
+    /// resolv.conf `options` applied to every generated block.
+    pub options: ResolvOptions,
+    /// Address/netmask pairs for the resolv.conf `sortlist` line, applied to
+    /// every generated block.
+    pub sortlist: Vec<String>,
+    /// Which [`DnsBackend`](crate::resolvconf::DnsBackend) to write DNS
+    /// information through. Overridable with `--backend`.
+    pub backend: Option<BackendKind>,
+    /// Overrides the interface name a service's DNS information is filed
+    /// under (keyed by service ID or ConnMan interface name), in case it
+    /// needs to differ from the one ConnMan reports.
+    pub interfaces: HashMap<String, String>,
+}
+
+impl Config {
+    /// Loads the config from `path`.
+    ///
+    /// If `path` was not given with `-c`/`--config` (i.e. it's the default
+    /// path or came from `RESOLVCONF_CONFIG`) and the file doesn't exist, an
+    /// empty `Config` is returned instead of an error.
+    pub fn load(path: &Path, explicit: bool) -> Result<Config> {
+        match fs::read_to_string(path) {
+            Ok(content) => toml::from_str(&content)
+                .with_context(|| format!("Failed to parse config file {}", path.display())),
+            Err(e) if !explicit && e.kind() == ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(e).context(format!("Failed to read config file {}", path.display())),
+        }
+    }
+
+    /// Returns whether the given service should be managed, based on the
+    /// `allow`/`deny` lists (matched against both the service ID and its
+    /// interface name).
+    pub fn is_allowed(&self, service: &Service) -> bool {
+        let iface = service.interface_or_id();
+
+        if self.deny.contains(&service.id) || self.deny.contains(iface) {
+            return false;
+        }
+        self.allow.is_empty() || self.allow.contains(&service.id) || self.allow.contains(iface)
+    }
+
+    /// Returns the interface name `service`'s DNS information should be
+    /// registered under, applying the `interfaces` override (matched
+    /// against the service ID first, then its ConnMan interface name).
+    pub fn interface_for<'a>(&'a self, service: &'a Service) -> &'a str {
+        let iface = service.interface_or_id();
+        self.interfaces
+            .get(&service.id)
+            .or_else(|| self.interfaces.get(iface))
+            .map_or(iface, String::as_str)
+    }
+
+    /// Merges the globally-configured nameservers/domains/options/sortlist
+    /// into the service's own, so they appear in every rendered resolv.conf
+    /// block.
+    ///
+    /// Safe to call more than once for the same service (e.g. once in
+    /// `insert()` and again after every `update()`): entries already present
+    /// are not duplicated.
+    pub fn apply_overrides(&self, service: &mut Service) {
+        for ns in &self.append_nameservers {
+            if !service.nameservers.contains(ns) {
+                service.nameservers.push(ns.clone());
+            }
+        }
+        for domain in &self.append_domains {
+            if !service.domains.contains(domain) {
+                service.domains.push(domain.clone());
+            }
+        }
+        if service.options.is_empty() {
+            service.options = self.options.clone();
+        }
+        for entry in &self.sortlist {
+            if !service.sortlist.contains(entry) {
+                service.sortlist.push(entry.clone());
+            }
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use std::time::Duration;
 
-use anyhow::anyhow;
+use anyhow::{anyhow, Context};
 use dbus::arg;
 use dbus::blocking::Connection;
 use dbus::message::MatchRule;
@@ -28,7 +28,7 @@ mod net_connman {
 }
 
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Service {
     /// Service ID (the last part of the service D-Bus path)
     pub id: String,
@@ -40,6 +40,58 @@ pub struct Service {
     pub nameservers: Vec<String>,
     /// List of currently-used search domains
     pub domains: Vec<String>,
+    /// resolv.conf `options` to emit for this service.
+    ///
+    /// ConnMan has no per-service `options`/`sortlist` property to read over
+    /// D-Bus, so this is always empty until `Config::apply_overrides` copies
+    /// in the globally-configured value — it lives on `Service` rather than
+    /// being read straight from `Config` at render time so that the render
+    /// step doesn't need a `Config` reference of its own.
+    pub options: ResolvOptions,
+    /// Address/netmask pairs for the resolv.conf `sortlist` line. Config-only,
+    /// for the same reason as `options`.
+    pub sortlist: Vec<String>,
+}
+
+/// The subset of resolv.conf(5) `options` that we know how to render.
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(default)]
+pub struct ResolvOptions {
+    pub ndots: Option<u32>,
+    pub timeout: Option<u32>,
+    pub attempts: Option<u32>,
+    pub rotate: bool,
+    pub single_request: bool,
+}
+
+impl ResolvOptions {
+    /// Returns whether every option is at its default, i.e. whether the
+    /// `options` line should be omitted.
+    pub fn is_empty(&self) -> bool {
+        *self == ResolvOptions::default()
+    }
+
+    /// Renders the value for a resolv.conf `options` line, e.g.
+    /// `ndots:2 timeout:3 rotate`.
+    pub fn render(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(ndots) = self.ndots {
+            parts.push(format!("ndots:{}", ndots));
+        }
+        if let Some(timeout) = self.timeout {
+            parts.push(format!("timeout:{}", timeout));
+        }
+        if let Some(attempts) = self.attempts {
+            parts.push(format!("attempts:{}", attempts));
+        }
+        if self.rotate {
+            parts.push("rotate".to_string());
+        }
+        if self.single_request {
+            parts.push("single-request".to_string());
+        }
+        parts.join(" ")
+    }
 }
 
 impl Service {
@@ -95,6 +147,8 @@ impl TryFrom<(dbus::Path<'_>, arg::PropMap)> for Service {
             id,
             state,
             interface,
+            options: ResolvOptions::default(),
+            sortlist: vec![],
             nameservers,
             domains,
         })
@@ -132,6 +186,51 @@ impl arg::ReadAll for ServiceUpdate {
 }
 
 
+/// Raw arguments of the `net.connman.Manager` `ServicesChanged` signal:
+/// services that were added or had properties changed, and paths of
+/// services that were removed.
+struct ServicesChangedArgs {
+    changed: Vec<(dbus::Path<'static>, arg::PropMap)>,
+    removed: Vec<dbus::Path<'static>>,
+}
+
+impl arg::ReadAll for ServicesChangedArgs {
+    fn read(iter: &mut arg::Iter<'_>) -> Result<Self, arg::TypeMismatchError> {
+        Ok(ServicesChangedArgs {
+            changed: iter.read()?,
+            removed: iter.read()?,
+        })
+    }
+}
+
+
+/// Abstracts where [`Service`] data and update events come from, so the
+/// reconciliation logic in [`crate::ResolvconfState`] can be driven by
+/// something other than a live D-Bus connection (see [`mock::MockServiceSource`]).
+pub trait ServiceSource {
+    /// Returns all currently `online`/`ready` services.
+    fn get_active(&self) -> anyhow::Result<Vec<Service>>;
+
+    /// Returns the current state of a single service.
+    fn get(&self, id: &str) -> anyhow::Result<Service>;
+
+    /// Registers a callback for `net.connman.Service` `PropertyChanged`. The
+    /// callback is handed back the source it was registered on, so it can
+    /// fetch the full, current state of the service the event is about (e.g.
+    /// `get(id)` once a service becomes `ready`/`online`).
+    fn on_update(
+        &self,
+        callback: Box<dyn FnMut(&str, ServiceUpdate, &dyn ServiceSource) + Send>,
+    ) -> anyhow::Result<()>;
+
+    /// Registers a callback for `net.connman.Manager` `ServicesChanged`.
+    fn on_services_changed(
+        &self,
+        callback: Box<dyn FnMut(Vec<Service>, Vec<String>) + Send>,
+    ) -> anyhow::Result<()>;
+}
+
+
 pub struct Services<'a> {
     proxy: dbus::blocking::Proxy<'a, &'a Connection>,
 }
@@ -201,4 +300,284 @@ impl<'a> Services<'a> {
                 true
             })
     }
+
+    /// Subscribes to the `net.connman.Manager` `ServicesChanged` signal.
+    ///
+    /// Unlike [`Services::on_update`], this is the authoritative source for
+    /// service membership: it fires whenever a service is added or removed,
+    /// even without a clean `State=disconnect` transition (e.g. on an
+    /// abrupt unplug or a connman restart), so it should be used to
+    /// reconcile state rather than only relying on property updates.
+    pub fn on_services_changed<F>(&self, mut callback: F) -> Result<dbus::channel::Token, dbus::Error>
+    where
+        F: FnMut(Vec<Service>, Vec<String>, Services<'_>) + Send + 'static,
+    {
+        let rule = MatchRule::new_signal("net.connman.Manager", "ServicesChanged")
+            .with_sender(BUS_NAME);
+        let timeout = self.proxy.timeout;
+
+        self.proxy
+            .connection
+            .add_match(rule, move |value: ServicesChangedArgs, conn, _msg| {
+                let changed = value
+                    .changed
+                    .into_iter()
+                    .filter_map(|rec| match Service::try_from(rec) {
+                        Ok(o) => Some(o),
+                        Err(e) => {
+                            warn!("{:#}", e);
+                            None
+                        }
+                    })
+                    .collect();
+
+                let removed = value
+                    .removed
+                    .into_iter()
+                    .filter_map(|path| match path.strip_prefix(Self::SERVICE_PATH_PREFIX) {
+                        Some(id) => Some(id.to_string()),
+                        None => {
+                            warn!("Received DBus Message with unexpected path: {}", path);
+                            None
+                        }
+                    })
+                    .collect();
+
+                callback(changed, removed, Services::new(conn, timeout));
+                true
+            })
+    }
+
+    /// Subscribes to `org.freedesktop.DBus` `NameOwnerChanged` for
+    /// [`BUS_NAME`] itself, invoking `callback` when ConnMan's well-known bus
+    /// name loses its owner (e.g. the connman process exits or is restarted).
+    ///
+    /// Unlike a transport-level error from [`dbus::blocking::Connection::process`],
+    /// this fires even if the system bus stays up the whole time, so it's
+    /// the only reliable way to notice connman going away.
+    pub fn on_connman_owner_lost<F>(&self, mut callback: F) -> Result<dbus::channel::Token, dbus::Error>
+    where
+        F: FnMut() + Send + 'static,
+    {
+        let rule = MatchRule::new_signal("org.freedesktop.DBus", "NameOwnerChanged")
+            .with_sender("org.freedesktop.DBus");
+
+        self.proxy.connection.add_match(rule, move |args: (String, String, String), _conn, _msg| {
+            let (name, _old_owner, new_owner) = args;
+            if name == BUS_NAME && new_owner.is_empty() {
+                callback();
+            }
+            true
+        })
+    }
+}
+
+impl<'a> ServiceSource for Services<'a> {
+    fn get_active(&self) -> anyhow::Result<Vec<Service>> {
+        Services::get_active(self)
+    }
+
+    fn get(&self, id: &str) -> anyhow::Result<Service> {
+        Services::get(self, id)
+    }
+
+    fn on_update(
+        &self,
+        mut callback: Box<dyn FnMut(&str, ServiceUpdate, &dyn ServiceSource) + Send>,
+    ) -> anyhow::Result<()> {
+        Services::on_update(self, move |id, update, services| callback(id, update, &services))
+            .map(|_token| ())
+            .context("Failed to subscribe to PropertyChanged")
+    }
+
+    fn on_services_changed(
+        &self,
+        mut callback: Box<dyn FnMut(Vec<Service>, Vec<String>) + Send>,
+    ) -> anyhow::Result<()> {
+        Services::on_services_changed(self, move |changed, removed, _services| {
+            callback(changed, removed)
+        })
+        .map(|_token| ())
+        .context("Failed to subscribe to ServicesChanged")
+    }
+}
+
+
+/// An in-memory [`ServiceSource`] for unit tests, driven by pushing canned
+/// services and synthetic events instead of talking to a live ConnMan.
+#[cfg(test)]
+pub mod mock {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::{Service, ServiceSource, ServiceUpdate};
+
+    #[derive(Default)]
+    struct State {
+        services: Vec<Service>,
+        update_cb: Option<Box<dyn FnMut(&str, ServiceUpdate, &dyn ServiceSource) + Send>>,
+        changed_cb: Option<Box<dyn FnMut(Vec<Service>, Vec<String>) + Send>>,
+    }
+
+    #[derive(Clone, Default)]
+    pub struct MockServiceSource {
+        state: Rc<RefCell<State>>,
+    }
+
+    impl MockServiceSource {
+        pub fn new() -> MockServiceSource {
+            MockServiceSource::default()
+        }
+
+        /// Sets the services returned by [`ServiceSource::get_active`] and
+        /// [`ServiceSource::get`].
+        pub fn set_services(&self, services: Vec<Service>) {
+            self.state.borrow_mut().services = services;
+        }
+
+        /// Synthesizes a `PropertyChanged` event for the registered callback.
+        pub fn fire_update(&self, id: &str, update: ServiceUpdate) {
+            // Take the callback out (rather than holding `borrow_mut()` across
+            // the call) so that a callback calling back into `get`/`get_active`
+            // doesn't panic on a re-entrant borrow of `state`.
+            let mut cb = self.state.borrow_mut().update_cb.take();
+            if let Some(cb) = cb.as_mut() {
+                cb(id, update, self);
+            }
+            self.state.borrow_mut().update_cb = cb;
+        }
+
+        /// Synthesizes a `ServicesChanged` event for the registered callback.
+        pub fn fire_services_changed(&self, changed: Vec<Service>, removed: Vec<String>) {
+            if let Some(cb) = self.state.borrow_mut().changed_cb.as_mut() {
+                cb(changed, removed);
+            }
+        }
+    }
+
+    impl ServiceSource for MockServiceSource {
+        fn get_active(&self) -> anyhow::Result<Vec<Service>> {
+            Ok(self.state.borrow().services.clone())
+        }
+
+        fn get(&self, id: &str) -> anyhow::Result<Service> {
+            self.state
+                .borrow()
+                .services
+                .iter()
+                .find(|s| s.id == id)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("No such service found: {}", id))
+        }
+
+        fn on_update(
+            &self,
+            callback: Box<dyn FnMut(&str, ServiceUpdate, &dyn ServiceSource) + Send>,
+        ) -> anyhow::Result<()> {
+            self.state.borrow_mut().update_cb = Some(callback);
+            Ok(())
+        }
+
+        fn on_services_changed(
+            &self,
+            callback: Box<dyn FnMut(Vec<Service>, Vec<String>) + Send>,
+        ) -> anyhow::Result<()> {
+            self.state.borrow_mut().changed_cb = Some(callback);
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        use super::*;
+
+        fn test_service(id: &str) -> Service {
+            Service {
+                id: id.to_string(),
+                state: "ready".to_string(),
+                interface: None,
+                nameservers: vec!["1.1.1.1".to_string()],
+                domains: vec![],
+                options: crate::connman::ResolvOptions::default(),
+                sortlist: vec![],
+            }
+        }
+
+        #[test]
+        fn get_active_returns_pushed_services() {
+            let source = MockServiceSource::new();
+            source.set_services(vec![test_service("eth")]);
+
+            let services = source.get_active().unwrap();
+            assert_eq!(services.len(), 1);
+            assert_eq!(services[0].id, "eth");
+        }
+
+        #[test]
+        fn get_returns_error_for_unknown_id() {
+            let source = MockServiceSource::new();
+            assert!(source.get("eth").is_err());
+        }
+
+        #[test]
+        fn fire_update_invokes_registered_callback() {
+            let source = MockServiceSource::new();
+            let received = Rc::new(RefCell::new(None));
+
+            let received_clone = Rc::clone(&received);
+            source
+                .on_update(Box::new(move |id, _update, _source| {
+                    *received_clone.borrow_mut() = Some(id.to_string());
+                }))
+                .unwrap();
+
+            source.fire_update("eth", ServiceUpdate::State("ready".to_string()));
+
+            assert_eq!(*received.borrow(), Some("eth".to_string()));
+        }
+
+        #[test]
+        fn fire_services_changed_invokes_registered_callback() {
+            let source = MockServiceSource::new();
+            let received = Rc::new(RefCell::new(None));
+
+            let received_clone = Rc::clone(&received);
+            source
+                .on_services_changed(Box::new(move |changed, removed| {
+                    *received_clone.borrow_mut() = Some((changed.len(), removed));
+                }))
+                .unwrap();
+
+            source.fire_services_changed(vec![test_service("eth")], vec!["wlan".to_string()]);
+
+            assert_eq!(*received.borrow(), Some((1, vec!["wlan".to_string()])));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ResolvOptions;
+
+    #[test]
+    fn resolv_options_render_with_all_fields_set() {
+        let options = ResolvOptions {
+            ndots: Some(2),
+            timeout: Some(3),
+            attempts: Some(1),
+            rotate: true,
+            single_request: true,
+        };
+
+        assert_eq!(options.render(), "ndots:2 timeout:3 attempts:1 rotate single-request");
+    }
+
+    #[test]
+    fn resolv_options_render_is_empty_for_unset_fields() {
+        assert_eq!(ResolvOptions::default().render(), "");
+        assert!(ResolvOptions::default().is_empty());
+    }
 }
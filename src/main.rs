@@ -4,21 +4,26 @@
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::env;
+use std::path::PathBuf;
 use std::process::exit;
 use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::thread;
+use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, bail, Context};
 use dbus::blocking::LocalConnection;
 use log::{error, info, trace, warn, LevelFilter};
-use signal_hook::consts::SIGTERM;
+use sd_notify::NotifyState;
+use signal_hook::consts::{SIGHUP, SIGTERM};
 use syslog::Facility;
 
-use connman::{Service, ServiceUpdate, Services};
-use resolvconf::Resolvconf;
+use config::Config;
+use connman::{Service, ServiceSource, ServiceUpdate, Services};
+use resolvconf::DnsBackend;
 
+mod config;
 mod connman;
 mod resolvconf;
 mod utils;
@@ -31,33 +36,59 @@ struct AppArgs {
     log_filter: String,
     cleanup_on_term: bool,
     syslog: bool,
+    config_path: PathBuf,
+    config_path_explicit: bool,
+    backend: Option<resolvconf::BackendKind>,
 }
 
 struct ResolvconfState {
-    resolvconf: Resolvconf,
+    resolvconf: Box<dyn DnsBackend>,
     services: HashMap<String, Service>,
+    config: Config,
 }
 
 impl ResolvconfState {
-    fn new() -> anyhow::Result<ResolvconfState> {
-        Ok(ResolvconfState {
+    fn new(resolvconf: Box<dyn DnsBackend>, config: Config) -> ResolvconfState {
+        ResolvconfState {
             services: HashMap::new(),
-            resolvconf: Resolvconf::new()?,
-        })
+            resolvconf,
+            config,
+        }
     }
 
-    fn insert(&mut self, service: Service) -> anyhow::Result<()> {
+    fn insert(&mut self, mut service: Service) -> anyhow::Result<()> {
+        if !self.config.is_allowed(&service) {
+            trace!("Ignoring filtered-out service: {}", service.id);
+            return Ok(());
+        }
+        self.config.apply_overrides(&mut service);
+
         // If we already have the given service with the same attributes, do nothing.
         if self.services.get(&service.id).map_or(false, |cur| *cur == service) {
             return Ok(());
         }
-        if !service.nameservers.is_empty() {
-            let iface = service.interface_or_id();
 
-            info!("Adding DNS information for {} ({})", iface, service.id);
-            self.resolvconf.add(iface, &service.resolvconf())?;
+        // `insert()` is also fed by `ServicesChanged`'s `changed` array, which
+        // (unlike the online/ready-filtered `get_active()`) can report a
+        // service in any state, e.g. `disconnect` (ConnMan keeps the service
+        // around on a plain Wi-Fi disconnect instead of removing it outright)
+        // or transient states like `configuration`/`association`/`failure`
+        // that still carry over a stale `Nameservers` value. Dispatch on
+        // state the same way `update()` does, instead of writing DNS records
+        // for any service with non-empty nameservers regardless of state.
+        match service.state.as_ref() {
+            "ready" | "online" if !service.nameservers.is_empty() => {
+                let iface = self.config.interface_for(&service);
+
+                info!("Adding DNS information for {} ({})", iface, service.id);
+                self.resolvconf.add(iface, &service)?;
+                self.services.insert(service.id.clone(), service);
+            }
+            "disconnect" => self.remove(&service.id)?,
+            _ => {
+                self.services.insert(service.id.clone(), service);
+            }
         }
-        self.services.insert(service.id.clone(), service);
 
         Ok(())
     }
@@ -66,18 +97,15 @@ impl ResolvconfState {
         if let Some(service) = self.services.get_mut(id) {
             // Update mutates the service.
             if service.update(&update) {
-                let iface = service.interface_or_id();
+                self.config.apply_overrides(service);
+                let iface = self.config.interface_for(service);
 
                 match service.state.as_ref() {
                     "ready" | "online" => {
                         info!("Updating DNS information for {} ({})", iface, service.id);
-                        self.resolvconf.add(iface, &service.resolvconf())?;
-                    }
-                    "disconnect" => {
-                        info!("Removing DNS information for {} ({})", iface, service.id);
-                        self.resolvconf.del(iface)?;
-                        self.services.remove(id);
+                        self.resolvconf.add(iface, &service)?;
                     }
+                    "disconnect" => self.remove(id)?,
                     "configuration" => (),  // ignore
                     _ => bail!("Unexpected service update in state {}: {:?}", service.state, update)
                 }
@@ -88,9 +116,21 @@ impl ResolvconfState {
         Ok(())
     }
 
+    /// Removes a single service, deleting its DNS information via the DNS backend.
+    fn remove(&mut self, id: &str) -> anyhow::Result<()> {
+        if let Some(service) = self.services.remove(id) {
+            let iface = self.config.interface_for(&service);
+
+            info!("Removing DNS information for {} ({})", iface, service.id);
+            self.resolvconf.del(iface)?;
+        }
+        Ok(())
+    }
+
     fn remove_all(&mut self) {
+        let config = self.config.clone();
         for (_, service) in self.services.drain() {
-            let iface = service.interface_or_id();
+            let iface = config.interface_for(&service);
 
             info!("Removing DNS information for {} ({})", iface, service.id);
             self.resolvconf
@@ -111,6 +151,12 @@ impl Service {
         for nameserver in self.nameservers.iter() {
             buf.push_str(&format!("nameserver {}\n", nameserver));
         }
+        if !self.options.is_empty() {
+            buf.push_str(&format!("options {}\n", self.options.render()));
+        }
+        if !self.sortlist.is_empty() {
+            buf.push_str(&format!("sortlist {}\n", self.sortlist.join(" ")));
+        }
         buf
     }
 }
@@ -121,6 +167,11 @@ fn main() {
         log_filter: env::var("RUST_LOG").unwrap_or_else(|_| "INFO".into()),
         cleanup_on_term: true,
         syslog: false,
+        config_path: env::var("RESOLVCONF_CONFIG")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| PathBuf::from(config::DEFAULT_CONFIG_PATH)),
+        config_path_explicit: false,
+        backend: None,
     };
 
     let mut iter = env::args().skip(1);
@@ -134,6 +185,29 @@ fn main() {
                     exit(100);
                 }
             }
+            "-c" | "--config" => {
+                if let Some(arg) = iter.next() {
+                    args.config_path = PathBuf::from(arg);
+                    args.config_path_explicit = true;
+                } else {
+                    eprintln!("{}: Option requires an argument: {}", PROG_NAME, opt);
+                    exit(100);
+                }
+            }
+            "--backend" => {
+                if let Some(arg) = iter.next() {
+                    match arg.parse() {
+                        Ok(backend) => args.backend = Some(backend),
+                        Err(e) => {
+                            eprintln!("{}: {:#}", PROG_NAME, e);
+                            exit(100);
+                        }
+                    }
+                } else {
+                    eprintln!("{}: Option requires an argument: {}", PROG_NAME, opt);
+                    exit(100);
+                }
+            }
             "-C" | "--no-cleanup-on-term" => {
                 args.cleanup_on_term = false;
             }
@@ -146,7 +220,7 @@ fn main() {
             }
             "-h" | "--help" => {
                 println!(
-                    "Usage: {} [--log <level>] [--no-cleanup-on-term] [--syslog] [--version] [--help]",
+                    "Usage: {} [--log-level <level>] [--config <path>] [--backend <auto|resolvconf|resolved>] [--no-cleanup-on-term] [--syslog] [--version] [--help]",
                     PROG_NAME
                 );
                 exit(0)
@@ -170,60 +244,211 @@ fn main() {
     };
 }
 
-fn run(args: &AppArgs) -> anyhow::Result<()> {
-    init_logger(args)?;
+/// Bounded exponential backoff for reconnect attempts, capped at `MAX_DELAY`.
+struct Backoff {
+    attempt: u32,
+}
 
-    info!("Starting {} {}", PROG_NAME, PROG_VERSION);
+impl Backoff {
+    const MAX_DELAY: Duration = Duration::from_secs(60);
 
-    let connection =
-        LocalConnection::new_system().context("Failed to connect to the system D-Bus")?;
+    fn new() -> Backoff {
+        Backoff { attempt: 0 }
+    }
 
-    let services = Services::new(&connection, Duration::from_millis(5000));
-    let mut resolvconf = ResolvconfState::new()?;
+    fn reset(&mut self) {
+        self.attempt = 0;
+    }
 
-    services
-        .get_active()?
-        .into_iter()
-        .try_for_each(|service| resolvconf.insert(service))?;
+    /// Sleeps for the current backoff delay, then increases it for next time.
+    fn sleep(&mut self) {
+        let delay = Duration::from_secs(1)
+            .saturating_mul(1 << self.attempt.min(6))
+            .min(Self::MAX_DELAY);
 
-    let resolvconf = Rc::new(RefCell::new(resolvconf));
+        warn!("Retrying in {:?} (attempt {})", delay, self.attempt + 1);
+        thread::sleep(delay);
+        self.attempt += 1;
+    }
+}
 
+/// Wires `state`'s reconciliation into `source`'s `PropertyChanged`/
+/// `ServicesChanged` events.
+///
+/// This is the glue `run()` uses to drive a live [`Services`] connection,
+/// extracted as a free function generic over [`ServiceSource`] so it can
+/// also be exercised in tests against [`connman::mock::MockServiceSource`].
+fn wire_dispatch(source: &impl ServiceSource, state: &Rc<RefCell<ResolvconfState>>) -> anyhow::Result<()> {
     {
-        let resolvconf = Rc::clone(&resolvconf);
-
-        services.on_update(move |id, update, services| {
-            trace!("Received PropertyChanged: {:?}", update);
-            match update {
-                ServiceUpdate::State(ref state) if state == "ready" || state == "online" => {
-                    match services.get(id) {
-                        Ok(service) => resolvconf
-                            .borrow_mut()
-                            .insert(service)
-                            .unwrap_or_else(|e| error!("{:#}", e)),
-                        Err(e) => error!("{:#}", e),
-                    }
-                }
-                _ => resolvconf
-                    .borrow_mut()
-                    .update(id, update)
-                    .unwrap_or_else(|e| error!("{:#}", e)),
-            };
-        })?;
+        let state = Rc::clone(state);
+        source.on_update(Box::new(move |id, update, source| {
+            dispatch_update(&state, id, update, source);
+        }))?;
     }
+    {
+        let state = Rc::clone(state);
+        source.on_services_changed(Box::new(move |changed, removed| {
+            dispatch_services_changed(&state, changed, removed);
+        }))?;
+    }
+    Ok(())
+}
+
+fn dispatch_update(
+    state: &Rc<RefCell<ResolvconfState>>,
+    id: &str,
+    update: ServiceUpdate,
+    source: &dyn ServiceSource,
+) {
+    trace!("Received PropertyChanged: {:?}", update);
+    match update {
+        ServiceUpdate::State(ref s) if s == "ready" || s == "online" => match source.get(id) {
+            Ok(service) => state.borrow_mut().insert(service).unwrap_or_else(|e| error!("{:#}", e)),
+            Err(e) => error!("{:#}", e),
+        },
+        _ => state.borrow_mut().update(id, update).unwrap_or_else(|e| error!("{:#}", e)),
+    }
+}
+
+fn dispatch_services_changed(state: &Rc<RefCell<ResolvconfState>>, changed: Vec<Service>, removed: Vec<String>) {
+    trace!("Received ServicesChanged: {} changed, {} removed", changed.len(), removed.len());
+    let mut state = state.borrow_mut();
+
+    for id in removed {
+        state.remove(&id).unwrap_or_else(|e| error!("{:#}", e));
+    }
+    for service in changed {
+        state.insert(service).unwrap_or_else(|e| error!("{:#}", e));
+    }
+}
+
+fn run(args: &AppArgs) -> anyhow::Result<()> {
+    init_logger(args)?;
+
+    info!("Starting {} {}", PROG_NAME, PROG_VERSION);
+
+    let mut config = Config::load(&args.config_path, args.config_path_explicit)?;
 
     let sigterm = Arc::new(AtomicBool::new(false));
     signal_hook::flag::register(SIGTERM, Arc::clone(&sigterm))
         .context("Failed to register SIGTERM handler")?;
+    let sighup = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(SIGHUP, Arc::clone(&sighup))
+        .context("Failed to register SIGHUP handler")?;
+
+    let mut watchdog_usec = 0;
+    let watchdog_interval = sd_notify::watchdog_enabled(false, &mut watchdog_usec)
+        .then(|| Duration::from_micros(watchdog_usec));
+    let mut backoff = Backoff::new();
 
     loop {
         if sigterm.load(Ordering::Relaxed) {
-            if args.cleanup_on_term {
-                info!("Caught SIGTERM, cleaning up and exiting...");
-                resolvconf.borrow_mut().remove_all();
-            }
             return Ok(());
         }
-        connection.process(Duration::from_millis(1000))?;
+
+        let connection = match LocalConnection::new_system() {
+            Ok(connection) => connection,
+            Err(e) => {
+                warn!("Failed to connect to the system D-Bus: {:#}", e);
+                backoff.sleep();
+                continue;
+            }
+        };
+
+        let backend_kind = args.backend.or(config.backend).unwrap_or_default();
+        let services = Services::new(&connection, Duration::from_millis(5000));
+        let backend = resolvconf::select_backend(&connection, backend_kind)?;
+        let mut state = ResolvconfState::new(backend, config.clone());
+
+        let reconciled = services
+            .get_active()
+            .and_then(|services| services.into_iter().try_for_each(|service| state.insert(service)));
+
+        if let Err(e) = reconciled {
+            warn!("Failed to reconcile the current service list: {:#}", e);
+            // Don't leak DNS records for services successfully added before
+            // the one that failed.
+            state.remove_all();
+            backoff.sleep();
+            continue;
+        }
+
+        sd_notify::notify(false, &[NotifyState::Ready])
+            .unwrap_or_else(|e| warn!("Failed to notify systemd of readiness: {}", e));
+        backoff.reset();
+
+        let state = Rc::new(RefCell::new(state));
+
+        wire_dispatch(&services, &state)?;
+
+        let connman_lost = Rc::new(RefCell::new(false));
+        {
+            let connman_lost = Rc::clone(&connman_lost);
+
+            services.on_connman_owner_lost(move || {
+                *connman_lost.borrow_mut() = true;
+            })?;
+        }
+
+        let mut last_watchdog = Instant::now();
+
+        let lost_connection = loop {
+            if connman_lost.replace(false) {
+                break dbus::Error::new_custom(
+                    "net.connman.Error.OwnerLost",
+                    "net.connman lost its owner on the system bus",
+                );
+            }
+
+            if sigterm.load(Ordering::Relaxed) {
+                if args.cleanup_on_term {
+                    info!("Caught SIGTERM, cleaning up and exiting...");
+                    sd_notify::notify(false, &[NotifyState::Stopping])
+                        .unwrap_or_else(|e| warn!("Failed to notify systemd of shutdown: {}", e));
+                    state.borrow_mut().remove_all();
+                }
+                return Ok(());
+            }
+
+            if sighup.swap(false, Ordering::Relaxed) {
+                info!("Caught SIGHUP, reloading config from {}", args.config_path.display());
+                sd_notify::notify(false, &[NotifyState::Reloading])
+                    .unwrap_or_else(|e| warn!("Failed to notify systemd of reload: {}", e));
+
+                match Config::load(&args.config_path, args.config_path_explicit) {
+                    Ok(new_config) => {
+                        config = new_config.clone();
+                        state.borrow_mut().config = new_config;
+                    }
+                    Err(e) => error!("Failed to reload config, keeping the old one: {:#}", e),
+                }
+
+                sd_notify::notify(false, &[NotifyState::Ready])
+                    .unwrap_or_else(|e| warn!("Failed to notify systemd of readiness: {}", e));
+            }
+
+            if let Err(e) = connection.process(Duration::from_millis(1000)) {
+                break e;
+            }
+
+            if let Some(interval) = watchdog_interval {
+                if last_watchdog.elapsed() >= interval / 2 {
+                    sd_notify::notify(false, &[NotifyState::Watchdog])
+                        .unwrap_or_else(|e| warn!("Failed to notify systemd watchdog: {}", e));
+                    last_watchdog = Instant::now();
+                }
+            }
+        };
+
+        warn!("Lost connection to the system D-Bus, reconnecting: {:#}", lost_connection);
+
+        // The backend/connection this state was writing through is gone, and
+        // the next iteration starts over with a fresh, empty `ResolvconfState`
+        // reconciled from scratch — clean up everything it was tracking now,
+        // or those DNS records would be orphaned forever.
+        state.borrow_mut().remove_all();
+
+        backoff.sleep();
     }
 }
 
@@ -244,3 +469,248 @@ fn init_logger(args: &AppArgs) -> anyhow::Result<()> {
     }
     Ok(())
 }
+
+
+#[cfg(test)]
+mod tests {
+    use connman::mock::MockServiceSource;
+    use connman::ResolvOptions;
+    use resolvconf::mock::{DnsCall, MockDnsBackend};
+
+    use super::*;
+
+    fn service(id: &str, state: &str, nameservers: &[&str]) -> Service {
+        Service {
+            id: id.to_string(),
+            state: state.to_string(),
+            interface: Some(format!("{}0", id)),
+            nameservers: nameservers.iter().map(|s| s.to_string()).collect(),
+            domains: vec![],
+            options: ResolvOptions::default(),
+            sortlist: vec![],
+        }
+    }
+
+    fn state_with(backend: MockDnsBackend) -> ResolvconfState {
+        ResolvconfState::new(Box::new(backend), Config::default())
+    }
+
+    #[test]
+    fn insert_adds_dns_for_service_with_nameservers() {
+        let backend = MockDnsBackend::new();
+        let mut state = state_with(backend.clone());
+
+        state.insert(service("eth", "ready", &["1.1.1.1"])).unwrap();
+
+        assert_eq!(
+            backend.calls(),
+            vec![DnsCall::Add {
+                interface: "eth0".to_string(),
+                nameservers: vec!["1.1.1.1".to_string()],
+                domains: vec![],
+            }]
+        );
+    }
+
+    #[test]
+    fn insert_skips_service_without_nameservers() {
+        let backend = MockDnsBackend::new();
+        let mut state = state_with(backend.clone());
+
+        state.insert(service("eth", "ready", &[])).unwrap();
+
+        assert!(backend.calls().is_empty());
+    }
+
+    #[test]
+    fn insert_is_idempotent_for_unchanged_service() {
+        let backend = MockDnsBackend::new();
+        let mut state = state_with(backend.clone());
+
+        state.insert(service("eth", "ready", &["1.1.1.1"])).unwrap();
+        state.insert(service("eth", "ready", &["1.1.1.1"])).unwrap();
+
+        assert_eq!(backend.calls().len(), 1);
+    }
+
+    #[test]
+    fn flapping_between_ready_and_disconnect_adds_then_removes() {
+        let backend = MockDnsBackend::new();
+        let mut state = state_with(backend.clone());
+
+        state.insert(service("eth", "ready", &["1.1.1.1"])).unwrap();
+        state.update("eth", ServiceUpdate::State("disconnect".to_string())).unwrap();
+
+        assert_eq!(
+            backend.calls(),
+            vec![
+                DnsCall::Add {
+                    interface: "eth0".to_string(),
+                    nameservers: vec!["1.1.1.1".to_string()],
+                    domains: vec![],
+                },
+                DnsCall::Del { interface: "eth0".to_string() },
+            ]
+        );
+        assert!(state.services.is_empty());
+    }
+
+    #[test]
+    fn insert_removes_service_reported_disconnected() {
+        let backend = MockDnsBackend::new();
+        let mut state = state_with(backend.clone());
+
+        // This is how a disconnect delivered via `ServicesChanged`'s `changed`
+        // array (rather than its `removed` array) reaches `insert()`.
+        state.insert(service("eth", "ready", &["1.1.1.1"])).unwrap();
+        state.insert(service("eth", "disconnect", &["1.1.1.1"])).unwrap();
+
+        assert_eq!(
+            backend.calls(),
+            vec![
+                DnsCall::Add {
+                    interface: "eth0".to_string(),
+                    nameservers: vec!["1.1.1.1".to_string()],
+                    domains: vec![],
+                },
+                DnsCall::Del { interface: "eth0".to_string() },
+            ]
+        );
+        assert!(state.services.is_empty());
+    }
+
+    #[test]
+    fn insert_skips_dns_for_service_in_transient_state() {
+        let backend = MockDnsBackend::new();
+        let mut state = state_with(backend.clone());
+
+        // ConnMan can report a service in e.g. `configuration`/`association`/
+        // `failure` via `ServicesChanged`'s `changed` array while it still
+        // carries over a stale `Nameservers` value from before.
+        state.insert(service("eth", "configuration", &["1.1.1.1"])).unwrap();
+
+        assert!(backend.calls().is_empty());
+        assert!(state.services.contains_key("eth"));
+    }
+
+    #[test]
+    fn update_for_unknown_service_is_ignored() {
+        let backend = MockDnsBackend::new();
+        let mut state = state_with(backend.clone());
+
+        state.update("eth", ServiceUpdate::State("ready".to_string())).unwrap();
+
+        assert!(backend.calls().is_empty());
+    }
+
+    #[test]
+    fn config_deny_list_filters_out_service() {
+        let backend = MockDnsBackend::new();
+        let mut config = Config::default();
+        config.deny.insert("eth".to_string());
+        let mut state = ResolvconfState::new(Box::new(backend.clone()), config);
+
+        state.insert(service("eth", "ready", &["1.1.1.1"])).unwrap();
+
+        assert!(backend.calls().is_empty());
+        assert!(state.services.is_empty());
+    }
+
+    #[test]
+    fn config_interface_override_is_used_for_dns_backend_calls() {
+        let backend = MockDnsBackend::new();
+        let mut config = Config::default();
+        config.interfaces.insert("eth".to_string(), "lan0".to_string());
+        let mut state = ResolvconfState::new(Box::new(backend.clone()), config);
+
+        state.insert(service("eth", "ready", &["1.1.1.1"])).unwrap();
+        state.update("eth", ServiceUpdate::State("disconnect".to_string())).unwrap();
+
+        assert_eq!(
+            backend.calls(),
+            vec![
+                DnsCall::Add {
+                    interface: "lan0".to_string(),
+                    nameservers: vec!["1.1.1.1".to_string()],
+                    domains: vec![],
+                },
+                DnsCall::Del { interface: "lan0".to_string() },
+            ]
+        );
+    }
+
+    #[test]
+    fn resolvconf_renders_options_and_sortlist_lines() {
+        let mut svc = service("eth", "ready", &["1.1.1.1", "8.8.8.8"]);
+        svc.domains = vec!["example.com".to_string()];
+        svc.options = ResolvOptions { ndots: Some(2), timeout: Some(3), rotate: true, ..Default::default() };
+        svc.sortlist = vec!["10.0.0.0/255.0.0.0".to_string()];
+
+        assert_eq!(
+            svc.resolvconf(),
+            "# Generated for eth\n\
+             search example.com\n\
+             nameserver 1.1.1.1\n\
+             nameserver 8.8.8.8\n\
+             options ndots:2 timeout:3 rotate\n\
+             sortlist 10.0.0.0/255.0.0.0\n"
+        );
+    }
+
+    #[test]
+    fn resolvconf_omits_options_and_sortlist_lines_when_unset() {
+        let svc = service("eth", "ready", &["1.1.1.1"]);
+
+        assert_eq!(svc.resolvconf(), "# Generated for eth\nnameserver 1.1.1.1\n");
+    }
+
+    #[test]
+    fn dispatch_update_fetches_full_service_once_it_becomes_ready() {
+        let backend = MockDnsBackend::new();
+        let state = Rc::new(RefCell::new(state_with(backend.clone())));
+
+        let source = MockServiceSource::new();
+        source.set_services(vec![service("eth", "ready", &["1.1.1.1"])]);
+        wire_dispatch(&source, &state).unwrap();
+
+        source.fire_update("eth", ServiceUpdate::State("ready".to_string()));
+
+        assert_eq!(
+            backend.calls(),
+            vec![DnsCall::Add {
+                interface: "eth0".to_string(),
+                nameservers: vec!["1.1.1.1".to_string()],
+                domains: vec![],
+            }]
+        );
+    }
+
+    #[test]
+    fn dispatch_services_changed_applies_changed_and_removed() {
+        let backend = MockDnsBackend::new();
+        let state = Rc::new(RefCell::new(state_with(backend.clone())));
+
+        let source = MockServiceSource::new();
+        wire_dispatch(&source, &state).unwrap();
+
+        state.borrow_mut().insert(service("wlan", "ready", &["8.8.8.8"])).unwrap();
+        source.fire_services_changed(vec![service("eth", "ready", &["1.1.1.1"])], vec!["wlan".to_string()]);
+
+        assert_eq!(
+            backend.calls(),
+            vec![
+                DnsCall::Add {
+                    interface: "wlan0".to_string(),
+                    nameservers: vec!["8.8.8.8".to_string()],
+                    domains: vec![],
+                },
+                DnsCall::Del { interface: "wlan0".to_string() },
+                DnsCall::Add {
+                    interface: "eth0".to_string(),
+                    nameservers: vec!["1.1.1.1".to_string()],
+                    domains: vec![],
+                },
+            ]
+        );
+    }
+}
@@ -1,32 +1,120 @@
 use std::env;
 use std::fs;
 use std::io::prelude::*;
+use std::net::IpAddr;
 use std::process::{Command, Stdio};
+use std::time::Duration;
 
-use anyhow::{bail, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
+use dbus::blocking::{Connection, Proxy};
 use log::{debug, trace};
+use nix::net::if_::if_nametoindex;
 use nix::unistd::Uid;
 
+use crate::connman::Service;
+use crate::utils;
+
 
 const DEFAULT_RESOLVCONF: &str = "/usr/sbin/resolvconf";
+const RESOLVED_BUS_NAME: &str = "org.freedesktop.resolve1";
+
+// `AF_INET`/`AF_INET6` as used by `SetLinkDNS`'s address family argument.
+const AF_INET: i32 = 2;
+const AF_INET6: i32 = 10;
+
+/// A backend that applies DNS information for an interface, either by
+/// writing it out (e.g. to `resolvconf(8)`) or by pushing it directly to a
+/// resolver daemon over D-Bus.
+pub trait DnsBackend {
+    /// Sets the DNS information of `interface` to that of `service`.
+    fn add(&self, interface: &str, service: &Service) -> Result<()>;
+
+    /// Clears the DNS information previously set for `interface`.
+    fn del(&self, interface: &str) -> Result<()>;
+}
+
+/// Which [`DnsBackend`] to use, selectable via the config file or
+/// `--backend`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BackendKind {
+    /// Auto-detect: prefer systemd-resolved if it owns its bus name on the
+    /// system bus, otherwise fall back to the `resolvconf(8)` binary.
+    Auto,
+    Resolvconf,
+    Resolved,
+}
+
+impl Default for BackendKind {
+    fn default() -> BackendKind {
+        BackendKind::Auto
+    }
+}
+
+impl std::str::FromStr for BackendKind {
+    type Err = anyhow::Error;
 
-/// Interface for calling `resolvconf(8)` command.
+    fn from_str(s: &str) -> Result<BackendKind> {
+        match s {
+            "auto" => Ok(BackendKind::Auto),
+            "resolvconf" => Ok(BackendKind::Resolvconf),
+            "resolved" => Ok(BackendKind::Resolved),
+            _ => bail!("Invalid backend: {} (expected auto, resolvconf or resolved)", s),
+        }
+    }
+}
+
+/// Picks a concrete [`DnsBackend`] for `kind`, resolving [`BackendKind::Auto`]
+/// by probing the system bus for systemd-resolved and otherwise requiring
+/// the `resolvconf(8)` binary (`$RESOLVCONF`, or `/usr/sbin/resolvconf` by
+/// default) to be found by [`utils::which`].
+pub fn select_backend(connection: &Connection, kind: BackendKind) -> Result<Box<dyn DnsBackend + '_>> {
+    match kind {
+        BackendKind::Resolvconf => Ok(Box::new(ResolvconfBackend::new())),
+        BackendKind::Resolved => Ok(Box::new(ResolvedBackend::new(connection))),
+        BackendKind::Auto if resolved_is_running(connection) => {
+            Ok(Box::new(ResolvedBackend::new(connection)))
+        }
+        BackendKind::Auto => {
+            utils::which(&resolvconf_path())
+                .context("Auto-detected the resolvconf(8) backend, but it could not be found")?;
+            Ok(Box::new(ResolvconfBackend::new()))
+        }
+    }
+}
+
+fn resolvconf_path() -> String {
+    env::var("RESOLVCONF").unwrap_or_else(|_| DEFAULT_RESOLVCONF.into())
+}
+
+fn resolved_is_running(connection: &Connection) -> bool {
+    let proxy = connection.with_proxy("org.freedesktop.DBus", "/", Duration::from_millis(5000));
+    proxy
+        .method_call::<(bool,), _, _, _>("org.freedesktop.DBus", "NameHasOwner", (RESOLVED_BUS_NAME,))
+        .map(|(has_owner,)| has_owner)
+        .unwrap_or(false)
+}
+
+
+/// Backend that shells out to the `resolvconf(8)` command.
 #[derive(Clone)]
-pub struct Resolvconf {
+pub struct ResolvconfBackend {
     path: String,
 }
 
-impl Resolvconf {
-    pub fn new() -> Resolvconf {
-        Resolvconf {
-            path: env::var("RESOLVCONF").unwrap_or_else(|_| DEFAULT_RESOLVCONF.into()),
-        }
+impl ResolvconfBackend {
+    pub fn new() -> ResolvconfBackend {
+        ResolvconfBackend { path: resolvconf_path() }
     }
+}
 
+impl DnsBackend for ResolvconfBackend {
     /// Adds DNS information to the specified interface (in resolv.conf format).
-    pub fn add(&self, interface: &str, content: &str) -> Result<()> {
+    fn add(&self, interface: &str, service: &Service) -> Result<()> {
         check_permissions(&self.path)?;
 
+        let content = service.resolvconf();
+
         debug!("Executing command: {} -a {}", self.path, interface);
         let mut child = Command::new(&self.path)
             .args(["-a", interface])
@@ -53,7 +141,7 @@ impl Resolvconf {
     }
 
     /// Deletes DNS information from the specified interface.
-    pub fn del(&self, interface: &str) -> Result<()> {
+    fn del(&self, interface: &str) -> Result<()> {
         check_permissions(&self.path)?;
 
         debug!("Executing command: {} -d {}", self.path, interface);
@@ -95,3 +183,152 @@ fn check_permissions(path: &str) -> Result<()> {
     }
     Ok(())
 }
+
+
+mod org_freedesktop_resolve1 {
+    use std::ops::Deref;
+
+    use dbus::blocking::{BlockingSender, Proxy};
+
+    pub trait Manager {
+        fn set_link_dns(&self, ifindex: i32, addresses: Vec<(i32, Vec<u8>)>) -> Result<(), dbus::Error>;
+        fn set_link_domains(&self, ifindex: i32, domains: Vec<(String, bool)>) -> Result<(), dbus::Error>;
+        fn revert_link(&self, ifindex: i32) -> Result<(), dbus::Error>;
+    }
+
+    impl<'a, T: BlockingSender, C: Deref<Target = T>> Manager for Proxy<'a, C> {
+        fn set_link_dns(&self, ifindex: i32, addresses: Vec<(i32, Vec<u8>)>) -> Result<(), dbus::Error> {
+            self.method_call("org.freedesktop.resolve1.Manager", "SetLinkDNS", (ifindex, addresses))
+        }
+
+        fn set_link_domains(&self, ifindex: i32, domains: Vec<(String, bool)>) -> Result<(), dbus::Error> {
+            self.method_call("org.freedesktop.resolve1.Manager", "SetLinkDomains", (ifindex, domains))
+        }
+
+        fn revert_link(&self, ifindex: i32) -> Result<(), dbus::Error> {
+            self.method_call("org.freedesktop.resolve1.Manager", "RevertLink", (ifindex,))
+        }
+    }
+}
+
+/// Backend that talks directly to systemd-resolved's `org.freedesktop.resolve1`
+/// manager over D-Bus, bypassing `resolvconf(8)` entirely.
+pub struct ResolvedBackend<'a> {
+    proxy: Proxy<'a, &'a Connection>,
+}
+
+impl<'a> ResolvedBackend<'a> {
+    pub fn new(connection: &'a Connection) -> ResolvedBackend<'a> {
+        ResolvedBackend {
+            proxy: connection.with_proxy(
+                RESOLVED_BUS_NAME,
+                "/org/freedesktop/resolve1",
+                Duration::from_millis(5000),
+            ),
+        }
+    }
+
+    fn ifindex(interface: &str) -> Result<i32> {
+        if_nametoindex(interface)
+            .map(|i| i as i32)
+            .with_context(|| format!("Failed to resolve ifindex of interface {}", interface))
+    }
+}
+
+impl<'a> DnsBackend for ResolvedBackend<'a> {
+    fn add(&self, interface: &str, service: &Service) -> Result<()> {
+        use org_freedesktop_resolve1::Manager;
+
+        let ifindex = Self::ifindex(interface)?;
+
+        let addresses = service
+            .nameservers
+            .iter()
+            .map(|ns| {
+                ns.parse::<IpAddr>()
+                    .map(|addr| match addr {
+                        IpAddr::V4(v4) => (AF_INET, v4.octets().to_vec()),
+                        IpAddr::V6(v6) => (AF_INET6, v6.octets().to_vec()),
+                    })
+                    .map_err(|e| anyhow!("Invalid nameserver address {}: {}", ns, e))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        debug!("Calling SetLinkDNS({}, ...) for {}", ifindex, interface);
+        self.proxy
+            .set_link_dns(ifindex, addresses)
+            .context("Failed to call SetLinkDNS on systemd-resolved")?;
+
+        let domains = service
+            .domains
+            .iter()
+            .map(|domain| (domain.clone(), false))
+            .collect::<Vec<_>>();
+
+        debug!("Calling SetLinkDomains({}, ...) for {}", ifindex, interface);
+        self.proxy
+            .set_link_domains(ifindex, domains)
+            .context("Failed to call SetLinkDomains on systemd-resolved")?;
+
+        Ok(())
+    }
+
+    fn del(&self, interface: &str) -> Result<()> {
+        use org_freedesktop_resolve1::Manager;
+
+        let ifindex = Self::ifindex(interface)?;
+
+        debug!("Calling RevertLink({}) for {}", ifindex, interface);
+        self.proxy
+            .revert_link(ifindex)
+            .context("Failed to call RevertLink on systemd-resolved")
+    }
+}
+
+
+/// An in-memory [`DnsBackend`] for unit tests, recording the exact sequence
+/// of `add`/`del` calls instead of touching the filesystem or D-Bus.
+#[cfg(test)]
+pub mod mock {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::{DnsBackend, Result, Service};
+
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub enum DnsCall {
+        Add { interface: String, nameservers: Vec<String>, domains: Vec<String> },
+        Del { interface: String },
+    }
+
+    #[derive(Clone, Default)]
+    pub struct MockDnsBackend {
+        calls: Rc<RefCell<Vec<DnsCall>>>,
+    }
+
+    impl MockDnsBackend {
+        pub fn new() -> MockDnsBackend {
+            MockDnsBackend::default()
+        }
+
+        pub fn calls(&self) -> Vec<DnsCall> {
+            self.calls.borrow().clone()
+        }
+    }
+
+    impl DnsBackend for MockDnsBackend {
+        fn add(&self, interface: &str, service: &Service) -> Result<()> {
+            self.calls.borrow_mut().push(DnsCall::Add {
+                interface: interface.to_string(),
+                nameservers: service.nameservers.clone(),
+                domains: service.domains.clone(),
+            });
+            Ok(())
+        }
+
+        fn del(&self, interface: &str) -> Result<()> {
+            self.calls.borrow_mut().push(DnsCall::Del { interface: interface.to_string() });
+            Ok(())
+        }
+    }
+}